@@ -1,6 +1,6 @@
 use alloy_primitives::{
     map::{HashMap, HashSet},
-    BlockNumber, B256,
+    Address, BlockNumber, B256,
 };
 use core::{
     marker::PhantomData,
@@ -19,6 +19,11 @@ use reth_trie::{
     prefix_set::{PrefixSetMut, TriePrefixSets},
     KeyHasher, Nibbles,
 };
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
 
 /// A wrapper around a database transaction that loads prefix sets within a given block range.
 #[derive(Debug)]
@@ -39,73 +44,591 @@ impl<TX, KH> Deref for PrefixSetLoader<'_, TX, KH> {
     }
 }
 
+/// The running, not-yet-frozen prefix sets accumulated during a load.
+///
+/// Collecting the running sets in one place makes windowed, incremental folding easy: each
+/// window folds its results in and is then dropped, keeping peak memory bound to a single
+/// window. The sets are kept as hashed `B256` keys (rather than as [`PrefixSetMut`] nibbles) to
+/// avoid re-unpacking nibbles and to let a checkpoint serialize the working set directly.
+#[derive(Debug, Default)]
+struct PrefixSetAccumulator {
+    account_prefix_set: HashSet<B256>,
+    storage_prefix_sets: HashMap<B256, HashSet<B256>>,
+    destroyed_accounts: HashSet<B256>,
+    /// Cache of already-computed address hashes. The account pass populates it and the storage
+    /// pass reuses it (computing only on a miss), so each distinct address is hashed once within
+    /// a window — a hot contract can recur across thousands of storage-changeset rows. It is
+    /// cleared at each window boundary so it never grows beyond one window's distinct addresses.
+    hashed_addresses: HashMap<Address, B256>,
+}
+
+impl PrefixSetAccumulator {
+    /// Hash the not-yet-cached `addresses` in parallel and insert them into
+    /// [`hashed_addresses`](Self::hashed_addresses).
+    ///
+    /// Addresses already present in the cache are skipped, so each distinct address has its
+    /// keccak computed only once per window.
+    fn hash_addresses<KH: KeyHasher>(&mut self, addresses: impl Iterator<Item = Address>) {
+        let mut to_hash = Vec::new();
+        let mut pending = HashSet::default();
+        for address in addresses {
+            if !self.hashed_addresses.contains_key(&address) && pending.insert(address) {
+                to_hash.push(address);
+            }
+        }
+
+        let hashed: Vec<_> =
+            to_hash.par_iter().map(|&address| (address, KH::hash_key(address))).collect();
+        self.hashed_addresses.extend(hashed);
+    }
+
+    /// Restore accumulator state from a checkpoint snapshot. The hash cache starts empty and is
+    /// rebuilt on demand by subsequent windows.
+    fn from_snapshot(snapshot: PrefixSetSnapshot) -> Self {
+        Self {
+            account_prefix_set: snapshot.account_prefix_set.into_iter().collect(),
+            storage_prefix_sets: snapshot
+                .storage_prefix_sets
+                .into_iter()
+                .map(|(k, v)| (k, v.into_iter().collect()))
+                .collect(),
+            destroyed_accounts: snapshot.destroyed_accounts,
+            hashed_addresses: HashMap::default(),
+        }
+    }
+
+    /// Produce a serializable snapshot using `last_block` as the last fully processed block number.
+    ///
+    /// The `B256` vectors are sorted so that two snapshots of the same logical set compare equal
+    /// regardless of the underlying [`HashSet`] iteration order. The encoded bytes are not
+    /// canonical: the storage map and the destroyed-account set are written in iteration order.
+    fn snapshot(&self, last_block: BlockNumber) -> PrefixSetSnapshot {
+        let mut account_prefix_set: Vec<B256> = self.account_prefix_set.iter().copied().collect();
+        account_prefix_set.sort_unstable();
+
+        PrefixSetSnapshot {
+            last_block,
+            account_prefix_set,
+            storage_prefix_sets: self
+                .storage_prefix_sets
+                .iter()
+                .map(|(k, v)| {
+                    let mut keys: Vec<B256> = v.iter().copied().collect();
+                    keys.sort_unstable();
+                    (*k, keys)
+                })
+                .collect(),
+            destroyed_accounts: self.destroyed_accounts.clone(),
+        }
+    }
+
+    /// Freeze the accumulated sets into the final [`TriePrefixSets`].
+    fn freeze(self) -> TriePrefixSets {
+        let mut account_prefix_set = PrefixSetMut::default();
+        for hashed_address in self.account_prefix_set {
+            account_prefix_set.insert(Nibbles::unpack(hashed_address));
+        }
+
+        let storage_prefix_sets = self
+            .storage_prefix_sets
+            .into_iter()
+            .map(|(hashed_address, keys)| {
+                let mut prefix_set = PrefixSetMut::default();
+                for hashed_key in keys {
+                    prefix_set.insert(Nibbles::unpack(hashed_key));
+                }
+                (hashed_address, prefix_set.freeze())
+            })
+            .collect();
+
+        TriePrefixSets {
+            account_prefix_set: account_prefix_set.freeze(),
+            storage_prefix_sets,
+            destroyed_accounts: self.destroyed_accounts,
+        }
+    }
+}
+
+/// A serializable checkpoint of an in-progress prefix-set rebuild.
+///
+/// Records the account/storage prefix sets and destroyed accounts accumulated so far, plus the
+/// last **fully processed** block number. Borrowing the bank-snapshot pattern of spilling the
+/// working set to disk: a full state-root rebuild over a large range can take a long time, and if
+/// the process dies mid-way the [`TriePrefixSets`] work is lost. A snapshot lets
+/// [`PrefixSetLoader::load_windowed_resumable`] checkpoint every N windows and resume from the
+/// checkpoint after a crash instead of restarting from the range start, still emitting a result
+/// identical to a single-shot load.
+///
+/// It is encoded in a compact fixed-width binary layout rather than JSON: these sets can hold
+/// hundreds of millions of `B256` entries, and JSON would both bloat the file and stringify every
+/// `B256` map key. Avoiding serde also keeps this core trie crate free of a `serde`/`serde_json`
+/// dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixSetSnapshot {
+    /// The last block number processed to completion (inclusive). Resume continues from
+    /// `last_block + 1`.
+    pub last_block: BlockNumber,
+    account_prefix_set: Vec<B256>,
+    storage_prefix_sets: HashMap<B256, Vec<B256>>,
+    destroyed_accounts: HashSet<B256>,
+}
+
+impl PrefixSetSnapshot {
+    /// Write the snapshot to `path` in the compact binary encoding.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.encode(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Read a previously written snapshot from `path`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        Self::decode(&mut reader)
+    }
+
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&self.last_block.to_le_bytes())?;
+        write_hashes(w, &self.account_prefix_set)?;
+        w.write_all(&(self.storage_prefix_sets.len() as u64).to_le_bytes())?;
+        for (hashed_address, keys) in &self.storage_prefix_sets {
+            w.write_all(hashed_address.as_slice())?;
+            write_hashes(w, keys)?;
+        }
+        w.write_all(&(self.destroyed_accounts.len() as u64).to_le_bytes())?;
+        for hash in &self.destroyed_accounts {
+            w.write_all(hash.as_slice())?;
+        }
+        Ok(())
+    }
+
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+        let last_block = read_u64(r)?;
+        let account_prefix_set = read_hashes(r)?;
+
+        let storage_len = read_u64(r)?;
+        let mut storage_prefix_sets = HashMap::default();
+        for _ in 0..storage_len {
+            let hashed_address = read_hash(r)?;
+            storage_prefix_sets.insert(hashed_address, read_hashes(r)?);
+        }
+
+        let destroyed_accounts = read_hashes(r)?.into_iter().collect();
+
+        Ok(Self { last_block, account_prefix_set, storage_prefix_sets, destroyed_accounts })
+    }
+}
+
+/// Upper bound on the number of `B256` entries pre-allocated from an untrusted on-disk length
+/// before any bytes are read, guarding against huge allocations from a corrupt checkpoint.
+const MAX_PREALLOC: u64 = 4096;
+
+fn write_hashes<W: Write>(w: &mut W, hashes: &[B256]) -> io::Result<()> {
+    w.write_all(&(hashes.len() as u64).to_le_bytes())?;
+    for hash in hashes {
+        w.write_all(hash.as_slice())?;
+    }
+    Ok(())
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_hash<R: Read>(r: &mut R) -> io::Result<B256> {
+    let mut buf = [0u8; 32];
+    r.read_exact(&mut buf)?;
+    Ok(B256::from(buf))
+}
+
+fn read_hashes<R: Read>(r: &mut R) -> io::Result<Vec<B256>> {
+    let len = read_u64(r)?;
+    // Never pre-allocate based purely on the on-disk length: a truncated or corrupt checkpoint
+    // could otherwise request a multi-GB allocation. Cap the reservation and let the `Vec` grow as
+    // entries are actually read.
+    let mut out = Vec::with_capacity(len.min(MAX_PREALLOC) as usize);
+    for _ in 0..len {
+        out.push(read_hash(r)?);
+    }
+    Ok(out)
+}
+
+/// Build a [`TriePrefixSets`] from an execution state diff, for stateless setups that have no
+/// changeset tables at all.
+///
+/// Workflows such as scroll's stateless-block-verifier and zk_evm's trace_decoder do not populate
+/// the `AccountChangeSets`/`StorageChangeSets` tables — they only have the in-memory state diff
+/// produced by executing the block. Like [`PrefixSetLoader::load`], this hashes the supplied
+/// addresses and storage keys in parallel with rayon, populates
+/// `account_prefix_set`/`storage_prefix_sets`, and writes accounts flagged destroyed straight into
+/// `destroyed_accounts` (no lookup against `HashedAccounts`). This lets the same trie-update
+/// machinery run in environments with no changeset tables.
+///
+/// `accounts` is deduplicated by address; if the same address appears both destroyed and not, the
+/// destroyed flag wins, matching the changeset path where a missing `HashedAccounts` row marks an
+/// account destroyed regardless of later touches. `storage` is deduplicated by `(address, key)`.
+///
+/// Stateless callers reach this through the crate-root re-export of the `prefix_set` module.
+pub fn from_state_diff<KH: KeyHasher>(
+    accounts: impl Iterator<Item = (Address, bool)>,
+    storage: impl Iterator<Item = (Address, B256)>,
+) -> TriePrefixSets {
+    // 按地址去重；同一地址若既被标记销毁又未被标记，销毁标记胜出
+    let mut account_destroyed: HashMap<Address, bool> = HashMap::default();
+    for (address, destroyed) in accounts {
+        let entry = account_destroyed.entry(address).or_insert(false);
+        *entry |= destroyed;
+    }
+    let accounts: Vec<(Address, bool)> = account_destroyed.into_iter().collect();
+
+    // 按 (address, key) 去重，使每个唯一组合只哈希一次
+    let mut seen_storage = HashSet::default();
+    let storage: Vec<(Address, B256)> =
+        storage.filter(|entry| seen_storage.insert(*entry)).collect();
+
+    let mut acc = PrefixSetAccumulator::default();
+
+    // 并行哈希全部涉及的地址（账户与存储共用缓存），每个地址只算一次
+    acc.hash_addresses::<KH>(
+        accounts
+            .iter()
+            .map(|(address, _)| *address)
+            .chain(storage.iter().map(|(address, _)| *address)),
+    );
+
+    // 账户：插入前缀集合，销毁标记直接落入 destroyed_accounts
+    for &(address, destroyed) in &accounts {
+        let hashed_address = acc.hashed_addresses[&address];
+        acc.account_prefix_set.insert(hashed_address);
+        if destroyed {
+            acc.destroyed_accounts.insert(hashed_address);
+        }
+    }
+
+    // 并行哈希唯一存储键，地址哈希取自缓存
+    let storage_hashes: Vec<_> = storage
+        .par_iter()
+        .map(|&(address, key)| (acc.hashed_addresses[&address], KH::hash_key(key)))
+        .collect();
+    for (hashed_address, hashed_key) in storage_hashes {
+        acc.account_prefix_set.insert(hashed_address);
+        acc.storage_prefix_sets.entry(hashed_address).or_default().insert(hashed_key);
+    }
+
+    acc.freeze()
+}
+
 impl<TX: DbTx, KH: KeyHasher> PrefixSetLoader<'_, TX, KH> {
     /// Load all account and storage changes for the given block range.
     pub fn load(self, range: RangeInclusive<BlockNumber>) -> Result<TriePrefixSets, DatabaseError> {
-        let mut account_prefix_set = PrefixSetMut::default();
-        let mut storage_prefix_sets = HashMap::<B256, PrefixSetMut>::default();
-        let mut destroyed_accounts = HashSet::default();
+        let mut acc = PrefixSetAccumulator::default();
+        self.accumulate(range, &mut acc)?;
+        Ok(acc.freeze())
+    }
 
-        // 收集所有需要处理的地址和存储键
-        let mut addresses = Vec::new();
-        let mut storage_entries = Vec::new();
+    /// Equivalent to [`load`](Self::load), but loads in block-sized windows to bound peak memory.
+    ///
+    /// Over a large range (e.g. rebuilding the state root from genesis), [`load`](Self::load)
+    /// buffers every changed address and every `(address, key)` storage entry across the whole
+    /// range into `Vec`s before processing them in parallel, potentially holding hundreds of
+    /// millions of records at once. This method processes only `window` blocks at a time: it walks
+    /// that window's changeset cursors, hashes the window's keys in parallel with rayon, folds the
+    /// results into the running prefix sets, then drops the window's data before advancing. The
+    /// frozen [`TriePrefixSets`] is identical to the non-windowed path; only peak memory differs.
+    pub fn load_windowed(
+        self,
+        range: RangeInclusive<BlockNumber>,
+        window: u64,
+    ) -> Result<TriePrefixSets, DatabaseError> {
+        self.load_windowed_resumable(range, window, None, 0, |_| Ok(()))
+    }
 
-        // 收集账户变更数据
+    /// A checkpointing, resumable variant of [`load_windowed`](Self::load_windowed).
+    ///
+    /// On top of windowed loading this supports crash recovery: if a `resume_from` snapshot is
+    /// given, loading continues from its `last_block + 1`; otherwise it starts at the range start.
+    /// Every `checkpoint_interval` windows (disabled when 0) the `checkpoint` callback is invoked
+    /// with the current working set so the caller can persist the [`PrefixSetSnapshot`]. Whether or
+    /// not a run is resumed, the frozen [`TriePrefixSets`] is identical to a single-shot
+    /// [`load`](Self::load).
+    pub fn load_windowed_resumable(
+        self,
+        range: RangeInclusive<BlockNumber>,
+        window: u64,
+        resume_from: Option<PrefixSetSnapshot>,
+        checkpoint_interval: u64,
+        mut checkpoint: impl FnMut(&PrefixSetSnapshot) -> Result<(), DatabaseError>,
+    ) -> Result<TriePrefixSets, DatabaseError> {
+        // 窗口大小至少为 1，避免空窗口导致死循环。
+        let window = window.max(1);
+        let end = *range.end();
+
+        let (mut acc, mut start) = match resume_from {
+            // 快照已包含直到 last_block 的结果，从下一个块继续。
+            Some(snapshot) => {
+                let resume_at = snapshot.last_block.saturating_add(1).max(*range.start());
+                (PrefixSetAccumulator::from_snapshot(snapshot), resume_at)
+            }
+            None => (PrefixSetAccumulator::default(), *range.start()),
+        };
+
+        let mut windows_since_checkpoint = 0u64;
+        while start <= end {
+            let window_end = start.saturating_add(window - 1).min(end);
+            self.accumulate(start..=window_end, &mut acc)?;
+
+            // 在最后一个窗口上不再 checkpoint —— 调用方拿到的是最终结果本身。
+            windows_since_checkpoint += 1;
+            if checkpoint_interval != 0 &&
+                windows_since_checkpoint == checkpoint_interval &&
+                window_end != end
+            {
+                checkpoint(&acc.snapshot(window_end))?;
+                windows_since_checkpoint = 0;
+            }
+
+            if window_end == end {
+                break;
+            }
+            start = window_end + 1;
+        }
+
+        Ok(acc.freeze())
+    }
+
+    /// Process a single block range, folding its account and storage changes into `acc`.
+    ///
+    /// This does not freeze the accumulated sets, so the caller can invoke it repeatedly over
+    /// consecutive windows to accumulate incrementally. Only the per-window address-hash cache is
+    /// cleared before returning.
+    fn accumulate(
+        &self,
+        range: RangeInclusive<BlockNumber>,
+        acc: &mut PrefixSetAccumulator,
+    ) -> Result<(), DatabaseError> {
+        // 收集账户变更数据并按地址去重，避免同一地址被重复哈希或重复做 DB 探测
+        let mut addresses = Vec::new();
+        let mut seen_addresses = HashSet::default();
         let mut account_changeset_cursor = self.cursor_read::<tables::AccountChangeSets>()?;
         let mut account_hashed_state_cursor = self.cursor_read::<tables::HashedAccounts>()?;
         for account_entry in account_changeset_cursor.walk_range(range.clone())? {
             let (_, AccountBeforeTx { address, .. }) = account_entry?;
-            addresses.push(address);
+            if seen_addresses.insert(address) {
+                addresses.push(address);
+            }
         }
 
-        // 并行计算地址哈希
-        let hashed_addresses: Vec<_> = addresses
-            .par_iter()
-            .map(|&address| (address, KH::hash_key(address)))
-            .collect();
+        // 仅并行计算尚未缓存的地址哈希，并写入缓存
+        acc.hash_addresses::<KH>(addresses.iter().copied());
 
         // 处理地址哈希结果
-        for (_, hashed_address) in &hashed_addresses {
-            account_prefix_set.insert(Nibbles::unpack(*hashed_address));
-            if account_hashed_state_cursor.seek_exact(*hashed_address)?.is_none() {
-                destroyed_accounts.insert(*hashed_address);
+        for address in &addresses {
+            let hashed_address = acc.hashed_addresses[address];
+            acc.account_prefix_set.insert(hashed_address);
+            if account_hashed_state_cursor.seek_exact(hashed_address)?.is_none() {
+                acc.destroyed_accounts.insert(hashed_address);
             }
         }
 
-        // 收集存储变更数据
+        // 收集存储变更数据并按 (address, key) 去重，使每个唯一组合只哈希一次
+        let mut storage_entries = Vec::new();
+        let mut seen_storage = HashSet::default();
         let mut storage_cursor = self.cursor_dup_read::<tables::StorageChangeSets>()?;
         let storage_range = BlockNumberAddress::range(range);
         for storage_entry in storage_cursor.walk_range(storage_range)? {
             let (BlockNumberAddress((_, address)), StorageEntry { key, .. }) = storage_entry?;
-            storage_entries.push((address, key));
+            if seen_storage.insert((address, key)) {
+                storage_entries.push((address, key));
+            }
         }
 
-        // 并行计算存储键哈希
+        // 存储 pass 复用账户 pass 的地址哈希缓存，未命中的地址补算后入缓存
+        acc.hash_addresses::<KH>(storage_entries.iter().map(|(address, _)| *address));
+
+        // 按唯一 (address, key) 并行计算存储键哈希，地址哈希直接取自缓存
         let storage_hashes: Vec<_> = storage_entries
             .par_iter()
-            .map(|&(address, key)| {
-                let hashed_address = KH::hash_key(address);
-                let hashed_key = KH::hash_key(key);
-                (hashed_address, hashed_key)
-            })
+            .map(|&(address, key)| (acc.hashed_addresses[&address], KH::hash_key(key)))
             .collect();
 
         // 处理存储哈希结果
         for (hashed_address, hashed_key) in storage_hashes {
-            account_prefix_set.insert(Nibbles::unpack(hashed_address));
-            storage_prefix_sets
-                .entry(hashed_address)
-                .or_default()
-                .insert(Nibbles::unpack(hashed_key));
+            acc.account_prefix_set.insert(hashed_address);
+            acc.storage_prefix_sets.entry(hashed_address).or_default().insert(hashed_key);
         }
 
-        Ok(TriePrefixSets {
-            account_prefix_set: account_prefix_set.freeze(),
-            storage_prefix_sets: storage_prefix_sets
-                .into_iter()
-                .map(|(k, v)| (k, v.freeze()))
-                .collect(),
-            destroyed_accounts,
-        })
+        // 缓存只在本窗口内需要复用，窗口结束即清空，避免跨窗口累积到整段范围的不同地址
+        acc.hashed_addresses.clear();
+
+        Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::U256;
+    use reth_db_api::transaction::DbTxMut;
+    use reth_primitives_traits::Account;
+    use reth_provider::test_utils::create_test_provider_factory;
+    use reth_trie::KeccakKeyHasher;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    /// Collect a [`TriePrefixSets`] into order-independent structures so two logically equal
+    /// results compare equal regardless of underlying hash-map/hash-set iteration order.
+    fn normalize(
+        sets: &TriePrefixSets,
+    ) -> (Vec<Nibbles>, BTreeMap<B256, Vec<Nibbles>>, BTreeSet<B256>) {
+        let account: Vec<Nibbles> = sets.account_prefix_set.iter().cloned().collect();
+        let storage = sets
+            .storage_prefix_sets
+            .iter()
+            .map(|(k, v)| (*k, v.iter().cloned().collect::<Vec<_>>()))
+            .collect();
+        let destroyed = sets.destroyed_accounts.iter().copied().collect();
+        (account, storage, destroyed)
+    }
+
+    #[test]
+    fn snapshot_round_trips_through_disk() {
+        let a1 = B256::with_last_byte(1);
+        let a2 = B256::with_last_byte(2);
+        let a3 = B256::with_last_byte(3);
+
+        let mut acc = PrefixSetAccumulator::default();
+        acc.account_prefix_set.extend([a1, a2, a3]);
+        acc.storage_prefix_sets
+            .entry(a1)
+            .or_default()
+            .extend([B256::with_last_byte(10), B256::with_last_byte(11)]);
+        acc.storage_prefix_sets.entry(a2).or_default().insert(B256::with_last_byte(20));
+        acc.destroyed_accounts.insert(a3);
+
+        let snapshot = acc.snapshot(42);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prefix_set.snapshot");
+        snapshot.save(&path).unwrap();
+        let loaded = PrefixSetSnapshot::load(&path).unwrap();
+
+        assert_eq!(loaded.last_block, 42);
+        assert_eq!(snapshot, loaded);
+        assert_eq!(
+            normalize(&acc.freeze()),
+            normalize(&PrefixSetAccumulator::from_snapshot(loaded).freeze()),
+        );
+    }
+
+    #[test]
+    fn windowed_and_resumed_match_single_shot_load() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+        let tx = provider.tx_ref();
+
+        let addr_a = Address::with_last_byte(1);
+        let addr_b = Address::with_last_byte(2);
+        let addr_c = Address::with_last_byte(3);
+
+        // Account changesets spread across the range; `addr_a` recurs in multiple blocks.
+        tx.put::<tables::AccountChangeSets>(1, AccountBeforeTx { address: addr_a, info: None })
+            .unwrap();
+        tx.put::<tables::AccountChangeSets>(2, AccountBeforeTx { address: addr_b, info: None })
+            .unwrap();
+        tx.put::<tables::AccountChangeSets>(4, AccountBeforeTx { address: addr_a, info: None })
+            .unwrap();
+        tx.put::<tables::AccountChangeSets>(6, AccountBeforeTx { address: addr_c, info: None })
+            .unwrap();
+
+        // `addr_a`/`addr_b` have hashed-account rows (not destroyed); `addr_c` is absent (destroyed).
+        tx.put::<tables::HashedAccounts>(KeccakKeyHasher::hash_key(addr_a), Account::default())
+            .unwrap();
+        tx.put::<tables::HashedAccounts>(KeccakKeyHasher::hash_key(addr_b), Account::default())
+            .unwrap();
+
+        // Storage changesets, including a duplicate `(addr_a, key1)` in different blocks.
+        let key1 = B256::with_last_byte(11);
+        let key2 = B256::with_last_byte(12);
+        tx.put::<tables::StorageChangeSets>(
+            BlockNumberAddress((1, addr_a)),
+            StorageEntry { key: key1, value: U256::ZERO },
+        )
+        .unwrap();
+        tx.put::<tables::StorageChangeSets>(
+            BlockNumberAddress((3, addr_a)),
+            StorageEntry { key: key2, value: U256::ZERO },
+        )
+        .unwrap();
+        tx.put::<tables::StorageChangeSets>(
+            BlockNumberAddress((4, addr_a)),
+            StorageEntry { key: key1, value: U256::ZERO },
+        )
+        .unwrap();
+        tx.put::<tables::StorageChangeSets>(
+            BlockNumberAddress((5, addr_b)),
+            StorageEntry { key: key1, value: U256::ZERO },
+        )
+        .unwrap();
+
+        let range = 1..=6;
+        let full = PrefixSetLoader::<_, KeccakKeyHasher>::new(tx).load(range.clone()).unwrap();
+
+        // A window smaller than the range, an exact divisor, and a window larger than the range.
+        for window in [1u64, 2, 100] {
+            let windowed = PrefixSetLoader::<_, KeccakKeyHasher>::new(tx)
+                .load_windowed(range.clone(), window)
+                .unwrap();
+            assert_eq!(normalize(&full), normalize(&windowed), "window size {window}");
+        }
+
+        // Capture a mid-range checkpoint, then resume from it and confirm the final result matches.
+        let mut captured: Option<PrefixSetSnapshot> = None;
+        PrefixSetLoader::<_, KeccakKeyHasher>::new(tx)
+            .load_windowed_resumable(range.clone(), 2, None, 1, |snapshot| {
+                if captured.is_none() {
+                    captured = Some(snapshot.clone());
+                }
+                Ok(())
+            })
+            .unwrap();
+        let snapshot = captured.expect("a checkpoint should have been emitted mid-range");
+        assert!(snapshot.last_block < *range.end());
+
+        let resumed = PrefixSetLoader::<_, KeccakKeyHasher>::new(tx)
+            .load_windowed_resumable(range, 2, Some(snapshot), 0, |_| Ok(()))
+            .unwrap();
+        assert_eq!(normalize(&full), normalize(&resumed));
+    }
+
+    #[test]
+    fn from_state_diff_dedups_and_destroyed_wins() {
+        let a = Address::with_last_byte(1);
+        let b = Address::with_last_byte(2);
+        let key1 = B256::with_last_byte(10);
+        let key2 = B256::with_last_byte(11);
+
+        // `a` appears not-destroyed then destroyed (destroyed must win); `b` appears twice,
+        // never destroyed. Storage repeats `(a, key1)` and touches `(a, key2)` and `(b, key1)`.
+        let accounts = vec![(a, false), (a, true), (b, false), (b, false)];
+        let storage = vec![(a, key1), (a, key1), (a, key2), (b, key1)];
+
+        let sets = from_state_diff::<KeccakKeyHasher>(accounts.into_iter(), storage.into_iter());
+        let (account, storage_sets, destroyed) = normalize(&sets);
+
+        let ha = KeccakKeyHasher::hash_key(a);
+        let hb = KeccakKeyHasher::hash_key(b);
+
+        // Both accounts are in the account prefix set.
+        assert!(account.contains(&Nibbles::unpack(ha)));
+        assert!(account.contains(&Nibbles::unpack(hb)));
+
+        // Destroyed-wins: `a` is destroyed, `b` is not.
+        assert!(destroyed.contains(&ha));
+        assert!(!destroyed.contains(&hb));
+
+        // Storage deduped per unique `(address, key)`: `a` has two keys, `b` has one.
+        assert_eq!(storage_sets[&ha].len(), 2);
+        assert_eq!(storage_sets[&hb].len(), 1);
+    }
+}